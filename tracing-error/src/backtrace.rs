@@ -0,0 +1,269 @@
+use crate::layer::WithContext;
+use std::fmt;
+use tracing::{Metadata, Span};
+
+/// A captured trace of [`tracing`] span contexts.
+///
+/// `SpanTrace`s are captured by the [`in_current_span()`] extension methods,
+/// or directly via [`SpanTrace::capture()`], and are most commonly used by
+/// bundling them inside of an application's error types.
+///
+/// [`tracing`]: https://docs.rs/tracing
+/// [`in_current_span()`]: crate::InstrumentError::in_current_span
+#[derive(Clone)]
+pub struct SpanTrace {
+    span: Span,
+}
+
+#[derive(Clone)]
+enum SpanTraceStatus {
+    Unsupported,
+    Captured,
+}
+
+impl SpanTrace {
+    /// Capture the current `tracing` span context.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use tracing_error::SpanTrace;
+    ///
+    /// fn my_function() -> Result<(), Error> {
+    ///     // ...
+    ///     Err(Error::new(SpanTrace::capture()))
+    /// }
+    ///
+    /// struct Error {
+    ///     context: SpanTrace,
+    ///     // ...
+    /// }
+    ///
+    /// impl Error {
+    ///     pub fn new(context: SpanTrace) -> Self {
+    ///         Self {
+    ///             context,
+    ///             // ...
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn capture() -> Self {
+        Self {
+            span: Span::current(),
+        }
+    }
+
+    /// Runs a function for each span in the captured trace that recorded at least one field,
+    /// starting with the innermost span, until the function returns `false`.
+    ///
+    /// Spans without fields are skipped. If no span in the trace has fields, the function is
+    /// called once, for the innermost span, with an empty field string.
+    ///
+    /// This function is rarely used directly, and is instead used by the `Display`
+    /// implementation.
+    pub fn with_spans(&self, mut f: impl FnMut(&Metadata<'_>, &str) -> bool) {
+        self.span.with_subscriber(|(id, sub)| {
+            if let Some(getcx) = sub.downcast_ref::<WithContext>() {
+                getcx.with_context(sub, id, &mut |metadata, formatted_fields| {
+                    f(metadata, formatted_fields)
+                });
+            }
+        });
+    }
+
+    /// Runs a function for each span that [`SpanTrace::with_spans`] visits, until the function
+    /// returns `false`, exposing each span as a structured [`SpanTraceFrame`] rather than
+    /// pre-rendered text.
+    ///
+    /// This is useful for emitting a `SpanTrace` as structured data (e.g. for a JSON log record)
+    /// instead of through the `Display` implementation.
+    pub fn with_frames(&self, mut f: impl FnMut(&SpanTraceFrame<'_>) -> bool) {
+        self.with_spans(|metadata, fields| {
+            let frame = SpanTraceFrame { metadata, fields };
+            f(&frame)
+        })
+    }
+
+    /// Returns an owned, [`serde::Serialize`]-able snapshot of this `SpanTrace`.
+    ///
+    /// This is useful for including a `SpanTrace` as a structured field in a JSON log record or
+    /// other machine-readable diagnostic output, rather than embedding it as a multi-line string.
+    ///
+    /// [`serde::Serialize`]: https://docs.rs/serde/latest/serde/trait.Serialize.html
+    #[cfg(feature = "serde")]
+    pub fn as_data(&self) -> SpanTraceData {
+        let mut frames = Vec::new();
+        self.with_frames(|frame| {
+            frames.push(SpanTraceFrameData {
+                target: frame.target().to_string(),
+                name: frame.name().to_string(),
+                fields: frame.fields().to_string(),
+                file: frame.file().map(String::from),
+                line: frame.line(),
+            });
+            true
+        });
+
+        SpanTraceData { frames }
+    }
+
+    fn status(&self) -> SpanTraceStatus {
+        let mut span_seen = false;
+        self.with_spans(|_, _| {
+            span_seen = true;
+            false
+        });
+
+        if span_seen {
+            SpanTraceStatus::Captured
+        } else {
+            SpanTraceStatus::Unsupported
+        }
+    }
+}
+
+impl fmt::Display for SpanTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut span = 0;
+
+        self.with_spans(|metadata, fields| {
+            span += 1;
+            let name = metadata.name();
+
+            if fields.is_empty() {
+                writeln!(f, "{:>4}: {}", span, name).unwrap();
+            } else {
+                writeln!(f, "{:>4}: {}", span, name).unwrap();
+                writeln!(f, "      with {}", fields).unwrap();
+            }
+            writeln!(f, "    at {}:{}", metadata.file().unwrap_or("<unknown>"), metadata.line().unwrap_or(0)).unwrap();
+
+            true
+        });
+
+        if span == 0 {
+            match self.status() {
+                SpanTraceStatus::Unsupported => write!(
+                    f,
+                    "Warning: SpanTrace capture is Unsupported.\nEnsure an ErrorLayer is installed."
+                )?,
+                SpanTraceStatus::Captured => write!(f, "SpanTrace contains no captured spans")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A single frame of a captured [`SpanTrace`], exposing the span's metadata and formatted
+/// fields as structured data rather than as part of a pre-rendered string.
+///
+/// This is produced by [`SpanTrace::with_frames`].
+#[derive(Debug)]
+pub struct SpanTraceFrame<'a> {
+    metadata: &'a Metadata<'a>,
+    fields: &'a str,
+}
+
+impl<'a> SpanTraceFrame<'a> {
+    /// Returns the target of the span, as described in [`Metadata::target`].
+    pub fn target(&self) -> &str {
+        self.metadata.target()
+    }
+
+    /// Returns the name of the span, as described in [`Metadata::name`].
+    pub fn name(&self) -> &str {
+        self.metadata.name()
+    }
+
+    /// Returns the span's fields, formatted as they would appear in the `Display` output.
+    pub fn fields(&self) -> &str {
+        self.fields
+    }
+
+    /// Returns the name of the source file where the span was created, if known.
+    pub fn file(&self) -> Option<&str> {
+        self.metadata.file()
+    }
+
+    /// Returns the line number in the source file where the span was created, if known.
+    pub fn line(&self) -> Option<u32> {
+        self.metadata.line()
+    }
+}
+
+/// An owned, [`serde::Serialize`]-able snapshot of a [`SpanTrace`], for use in structured
+/// logging or error-reporting backends.
+///
+/// This is produced by [`SpanTrace::as_data`].
+///
+/// [`serde::Serialize`]: https://docs.rs/serde/latest/serde/trait.Serialize.html
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct SpanTraceData {
+    frames: Vec<SpanTraceFrameData>,
+}
+
+#[cfg(feature = "serde")]
+impl SpanTraceData {
+    /// Returns the captured frames, starting with the innermost span.
+    pub fn frames(&self) -> &[SpanTraceFrameData] {
+        &self.frames
+    }
+}
+
+/// An owned snapshot of a single [`SpanTraceFrame`].
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct SpanTraceFrameData {
+    target: String,
+    name: String,
+    fields: String,
+    file: Option<String>,
+    line: Option<u32>,
+}
+
+impl fmt::Debug for SpanTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SpanTrace")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorLayer;
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn with_frames_exposes_span_metadata_and_fields() {
+        let subscriber = tracing_subscriber::Registry::default().with(ErrorLayer::default());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let span_trace = tracing::info_span!("my_span", answer = 42).in_scope(SpanTrace::capture);
+
+        let mut frames = Vec::new();
+        span_trace.with_frames(|frame| {
+            frames.push((frame.name().to_string(), frame.fields().to_string()));
+            true
+        });
+
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].0, "my_span");
+        assert!(frames[0].1.contains("answer"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn as_data_snapshot_matches_the_captured_frames() {
+        let subscriber = tracing_subscriber::Registry::default().with(ErrorLayer::default());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let span_trace = tracing::info_span!("my_span", answer = 42).in_scope(SpanTrace::capture);
+        let data = span_trace.as_data();
+
+        assert_eq!(data.frames().len(), 1);
+        assert_eq!(data.frames()[0].name, "my_span");
+        assert!(data.frames()[0].fields.contains("answer"));
+    }
+}
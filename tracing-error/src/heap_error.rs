@@ -0,0 +1,150 @@
+use crate::{ExtractSpanTrace, InstrumentError, InstrumentResult, SpanTrace, SpanTraces};
+use std::error::Error;
+use std::fmt;
+
+/// A wrapper type for `Error`s that bundles a `SpanTrace` with an existing
+/// error, storing the original error on the heap rather than the stack.
+pub struct TracedError {
+    inner: ErrorImpl,
+}
+
+struct ErrorImpl {
+    span_trace: SpanTrace,
+    error: Box<dyn Error + Send + Sync + 'static>,
+}
+
+impl TracedError {
+    // Note: this is an inherent method rather than an `impl<E> From<E> for TracedError` impl
+    // because `TracedError` itself implements `Error + Send + Sync + 'static`, so a blanket
+    // `From<E>` bounded the same way would overlap with the stdlib's reflexive
+    // `impl<T> From<T> for T` (E0119) once `E = TracedError`.
+    fn new<E>(error: E) -> Self
+    where
+        E: Error + Send + Sync + 'static,
+    {
+        crate::layer::emit_error_event(&error);
+        Self {
+            inner: ErrorImpl {
+                span_trace: SpanTrace::capture(),
+                error: Box::new(error),
+            },
+        }
+    }
+}
+
+impl fmt::Display for TracedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.inner.error, f)
+    }
+}
+
+impl fmt::Debug for TracedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner.error, f)?;
+        writeln!(f)?;
+        write!(f, "{}", self.inner.span_trace)
+    }
+}
+
+impl Error for TracedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.inner.error)
+    }
+
+    #[cfg(feature = "unstable-provider-api")]
+    fn provide<'a>(&'a self, request: &mut std::error::Request<'a>) {
+        request.provide_ref::<SpanTrace>(&self.inner.span_trace);
+        self.inner.error.provide(request);
+    }
+}
+
+impl<T, E> InstrumentResult<T> for Result<T, E>
+where
+    E: Error + Send + Sync + 'static,
+{
+    type Instrumented = TracedError;
+
+    fn in_current_span(self) -> Result<T, TracedError> {
+        self.map_err(TracedError::new)
+    }
+}
+
+impl<E> InstrumentError for E
+where
+    E: Error + Send + Sync + 'static,
+{
+    type Instrumented = TracedError;
+
+    fn in_current_span(self) -> Self::Instrumented {
+        TracedError::new(self)
+    }
+}
+
+impl ExtractSpanTrace for dyn Error + 'static {
+    fn span_trace(&self) -> Option<&SpanTrace> {
+        #[cfg(feature = "unstable-provider-api")]
+        {
+            if let Some(span_trace) = std::error::request_ref::<SpanTrace>(self) {
+                return Some(span_trace);
+            }
+        }
+
+        self.downcast_ref::<TracedError>()
+            .map(|traced| &traced.inner.span_trace)
+    }
+
+    fn span_traces(&self) -> SpanTraces<'_> {
+        SpanTraces::new(self)
+    }
+}
+
+#[cfg(all(test, feature = "unstable-provider-api"))]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct PlainError;
+
+    impl fmt::Display for PlainError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "plain error")
+        }
+    }
+
+    impl Error for PlainError {}
+
+    // Simulates a library like `anyhow`/`eyre` re-boxing a `TracedError` behind an opaque error
+    // type, so that `downcast_ref::<TracedError>()` can no longer find it.
+    struct Opaque(Box<dyn Error + Send + Sync + 'static>);
+
+    impl fmt::Debug for Opaque {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::Debug::fmt(&self.0, f)
+        }
+    }
+
+    impl fmt::Display for Opaque {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::Display::fmt(&self.0, f)
+        }
+    }
+
+    impl Error for Opaque {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            self.0.source()
+        }
+
+        fn provide<'a>(&'a self, request: &mut std::error::Request<'a>) {
+            self.0.provide(request)
+        }
+    }
+
+    #[test]
+    fn span_trace_is_recoverable_through_an_opaque_rebox() {
+        let traced = PlainError.in_current_span();
+        let opaque: Box<dyn Error + 'static> = Box::new(Opaque(Box::new(traced)));
+
+        assert!(opaque.downcast_ref::<TracedError>().is_none());
+        assert!(opaque.span_trace().is_some());
+    }
+}
@@ -0,0 +1,243 @@
+use std::{any::TypeId, fmt, marker::PhantomData};
+
+use tracing::{span, Dispatch, Level, Metadata, Subscriber};
+use tracing_subscriber::{
+    fmt::{
+        format::{DefaultFields, FormatFields},
+        FormattedFields,
+    },
+    layer::{self, Layer},
+    registry::LookupSpan,
+};
+
+/// Emits a `tracing` event carrying `error`'s `Display` output and a `traced = true` field, at
+/// the level configured via [`ErrorLayer::emit_events`] on the currently active subscriber's
+/// `ErrorLayer`, if any.
+///
+/// This is a no-op unless the active subscriber has an `ErrorLayer` that has had `emit_events`
+/// called on it — mirroring how [`SpanTrace::capture()`] only finds span context when an
+/// `ErrorLayer` is installed.
+///
+/// `tracing::event!` bakes its level into the callsite at compile time, so the configured level
+/// has to be dispatched to a literal `event!` invocation per `Level` variant rather than passed
+/// through as a value.
+///
+/// [`SpanTrace::capture()`]: crate::SpanTrace::capture
+pub(crate) fn emit_error_event(error: &dyn std::error::Error) {
+    let level = tracing::dispatcher::get_default(|dispatch| {
+        dispatch
+            .downcast_ref::<EmitEventsAt>()
+            .and_then(|emit_events_at| emit_events_at.0)
+    });
+
+    match level {
+        Some(Level::ERROR) => tracing::event!(Level::ERROR, traced = true, "{}", error),
+        Some(Level::WARN) => tracing::event!(Level::WARN, traced = true, "{}", error),
+        Some(Level::INFO) => tracing::event!(Level::INFO, traced = true, "{}", error),
+        Some(Level::DEBUG) => tracing::event!(Level::DEBUG, traced = true, "{}", error),
+        Some(Level::TRACE) => tracing::event!(Level::TRACE, traced = true, "{}", error),
+        None => {}
+    }
+}
+
+/// A [subscriber layer] that enables capturing [`SpanTrace`]s.
+///
+/// [`SpanTrace`]: crate::SpanTrace
+/// [subscriber layer]: tracing_subscriber::layer::Layer
+pub struct ErrorLayer<S, F = DefaultFields> {
+    format: F,
+    get_context: WithContext,
+    emit_events_at: EmitEventsAt,
+    _subscriber: PhantomData<fn(S)>,
+}
+
+impl<S> ErrorLayer<S, DefaultFields>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    /// Construct a new `ErrorLayer`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S, F> ErrorLayer<S, F>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    F: for<'writer> FormatFields<'writer> + 'static,
+{
+    /// Sets the field formatter that the layer being constructed will use to record fields.
+    pub fn with_format<F2>(self, format: F2) -> ErrorLayer<S, F2>
+    where
+        F2: for<'writer> FormatFields<'writer> + 'static,
+    {
+        ErrorLayer {
+            format,
+            get_context: WithContext(Self::get_context),
+            emit_events_at: self.emit_events_at,
+            _subscriber: self._subscriber,
+        }
+    }
+
+    /// Configures this layer to also emit a `tracing` event at `level` whenever an error is
+    /// instrumented via [`in_current_span()`], carrying the error's `Display` output and a
+    /// `traced = true` field, in the currently active span.
+    ///
+    /// This only takes effect while this layer is installed as part of the active subscriber,
+    /// the same way `SpanTrace` capture does. By default, no event is emitted.
+    ///
+    /// [`in_current_span()`]: crate::InstrumentError::in_current_span
+    pub fn emit_events(self, level: Level) -> Self {
+        Self {
+            emit_events_at: EmitEventsAt(Some(level)),
+            ..self
+        }
+    }
+
+    fn get_context(
+        dispatch: &Dispatch,
+        id: &span::Id,
+        f: &mut dyn FnMut(&Metadata<'_>, &str) -> bool,
+    ) {
+        let subscriber = dispatch
+            .downcast_ref::<S>()
+            .expect("subscriber should downcast to expected type; this is a bug!");
+        let span = subscriber
+            .span(id)
+            .expect("registry should have a span for the current ID");
+
+        let mut seen_at_least_one_field = false;
+        for span in span.scope() {
+            let ext = span.extensions();
+            if let Some(fields) = ext.get::<FormattedFields<F>>() {
+                if !fields.is_empty() {
+                    if !f(span.metadata(), fields.fields.as_str()) {
+                        return;
+                    }
+                    seen_at_least_one_field = true;
+                }
+            }
+        }
+
+        if !seen_at_least_one_field {
+            f(span.metadata(), "");
+        }
+    }
+}
+
+pub(crate) struct WithContext(
+    #[allow(clippy::type_complexity)]
+    pub(crate)  fn(&Dispatch, &span::Id, f: &mut dyn FnMut(&Metadata<'_>, &str) -> bool),
+);
+
+impl WithContext {
+    pub(crate) fn with_context(
+        &self,
+        dispatch: &Dispatch,
+        id: &span::Id,
+        f: &mut dyn FnMut(&Metadata<'_>, &str) -> bool,
+    ) {
+        (self.0)(dispatch, id, f)
+    }
+}
+
+/// Carries the `Level` an `ErrorLayer` was configured to [`emit_events`] at, if any, so that
+/// [`emit_error_event`] can find it through the active `Dispatch` without knowing the concrete
+/// `ErrorLayer<S, F>` it came from.
+///
+/// [`emit_events`]: ErrorLayer::emit_events
+pub(crate) struct EmitEventsAt(Option<Level>);
+
+impl<S, F> Layer<S> for ErrorLayer<S, F>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    F: for<'writer> FormatFields<'writer> + 'static,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: layer::Context<'_, S>) {
+        let span = ctx.span(id).expect("Missing span; this is a bug");
+        let mut extensions = span.extensions_mut();
+
+        if extensions.get_mut::<FormattedFields<F>>().is_none() {
+            let mut fields = FormattedFields::<F>::new(String::new());
+            if self.format.format_fields(fields.as_writer(), attrs).is_ok() {
+                extensions.insert(fields);
+            }
+        }
+    }
+
+    unsafe fn downcast_raw(&self, id: TypeId) -> Option<*const ()> {
+        match id {
+            id if id == TypeId::of::<Self>() => Some(self as *const _ as *const ()),
+            id if id == TypeId::of::<WithContext>() => Some(&self.get_context as *const _ as *const ()),
+            id if id == TypeId::of::<EmitEventsAt>() => Some(&self.emit_events_at as *const _ as *const ()),
+            _ => None,
+        }
+    }
+}
+
+impl<S> Default for ErrorLayer<S, DefaultFields>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    fn default() -> Self {
+        Self {
+            format: DefaultFields::new(),
+            get_context: WithContext(Self::get_context),
+            emit_events_at: EmitEventsAt(None),
+            _subscriber: PhantomData,
+        }
+    }
+}
+
+impl<S, F> fmt::Debug for ErrorLayer<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorLayer").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::prelude::*;
+
+    struct RecordingLayer {
+        emitted_level: Arc<Mutex<Option<Level>>>,
+    }
+
+    impl<S: Subscriber> Layer<S> for RecordingLayer {
+        fn on_event(&self, event: &tracing::Event<'_>, _ctx: layer::Context<'_, S>) {
+            *self.emitted_level.lock().unwrap() = Some(*event.metadata().level());
+        }
+    }
+
+    #[test]
+    fn emit_events_emits_at_the_configured_level() {
+        let emitted_level = Arc::new(Mutex::new(None));
+        let subscriber = tracing_subscriber::Registry::default()
+            .with(ErrorLayer::default().emit_events(Level::WARN))
+            .with(RecordingLayer {
+                emitted_level: emitted_level.clone(),
+            });
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        emit_error_event(&std::io::Error::new(std::io::ErrorKind::Other, "boom"));
+
+        assert_eq!(*emitted_level.lock().unwrap(), Some(Level::WARN));
+    }
+
+    #[test]
+    fn emit_error_event_is_a_no_op_by_default() {
+        let emitted_level = Arc::new(Mutex::new(None));
+        let subscriber = tracing_subscriber::Registry::default()
+            .with(ErrorLayer::default())
+            .with(RecordingLayer {
+                emitted_level: emitted_level.clone(),
+            });
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        emit_error_event(&std::io::Error::new(std::io::ErrorKind::Other, "boom"));
+
+        assert_eq!(*emitted_level.lock().unwrap(), None);
+    }
+}
@@ -29,6 +29,16 @@
 //! call on this dummy error to transmute the pointer to itself to a type erased version from which
 //! we can extract the actual SpanTrace.
 //!
+//! - `unstable-provider-api` - Implements `std::error::Error::provide` for `TracedError`, so that
+//! its `SpanTrace` (and any `std::backtrace::Backtrace` provided by the wrapped error) can be
+//! recovered from a `dyn Error` via [`std::error::request_ref`] without downcasting to
+//! `TracedError` first. This relies on the unstable `error_generic_member_access` language
+//! feature and therefore requires a nightly compiler.
+//!
+//! - `serde` - Adds [`SpanTrace::as_data`], which returns a [`SpanTraceData`] snapshot that
+//! derives `Serialize`, for emitting a `SpanTrace` as structured fields (e.g. in a JSON log
+//! record) instead of as a multi-line string.
+//!
 //! ## Usage
 //!
 //! Currently, `tracing-error` provides the [`SpanTrace`] type, which captures
@@ -96,12 +106,29 @@
 //! }
 //! ```
 //!
+//! By default, wrapping an error with [`in_current_span()`] only captures a [`SpanTrace`]; it
+//! does not record the failure in the event stream. Call [`ErrorLayer::emit_events`] to also
+//! emit a `tracing` event, at a chosen [`Level`], every time an error is instrumented:
+//!
+//! ```rust
+//! use tracing::Level;
+//! use tracing_error::ErrorLayer;
+//! use tracing_subscriber::prelude::*;
+//!
+//! let subscriber =
+//!     tracing_subscriber::Registry::default().with(ErrorLayer::default().emit_events(Level::ERROR));
+//! ```
+//!
 //! [`SpanTrace`]: struct.SpanTrace.html
 //! [`ErrorLayer`]: struct.ErrorLayer.html
 //! [span]: https://docs.rs/tracing/latest/tracing/span/index.html
 //! [event]: https://docs.rs/tracing/latest/tracing/struct.Event.html
 //! [subscriber layer]: https://docs.rs/tracing-subscriber/latest/tracing_subscriber/layer/trait.Layer.html
 //! [`tracing`]: https://docs.rs/tracing
+//! [`std::error::request_ref`]: https://doc.rust-lang.org/std/error/fn.request_ref.html
+//! [`in_current_span()`]: InstrumentError::in_current_span
+//! [`Level`]: https://docs.rs/tracing/latest/tracing/struct.Level.html
+#![cfg_attr(feature = "unstable-provider-api", feature(error_generic_member_access))]
 #![doc(html_root_url = "https://docs.rs/tracing-error/0.1.1")]
 #![warn(
     missing_debug_implementations,
@@ -132,7 +159,9 @@ mod layer;
 #[cfg(feature = "stack-error")]
 mod stack_error;
 
-pub use self::backtrace::SpanTrace;
+pub use self::backtrace::{SpanTrace, SpanTraceFrame};
+#[cfg(feature = "serde")]
+pub use self::backtrace::{SpanTraceData, SpanTraceFrameData};
 #[cfg(not(feature = "stack-error"))]
 pub use self::heap_error::TracedError;
 pub use self::layer::ErrorLayer;
@@ -198,6 +227,63 @@ pub trait ExtractSpanTrace {
     /// }
     /// ```
     fn span_trace(&self) -> Option<&SpanTrace>;
+
+    /// Walks the `source` chain starting at `self`, returning an iterator over every
+    /// `SpanTrace` captured along the way.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use tracing_error::ExtractSpanTrace;
+    /// use std::error::Error;
+    ///
+    /// fn print_span_traces(e: &(dyn Error + 'static)) {
+    ///     for span_trace in e.span_traces() {
+    ///         println!("{}", span_trace);
+    ///     }
+    /// }
+    /// ```
+    fn span_traces(&self) -> SpanTraces<'_>;
+}
+
+/// An iterator over the [`SpanTrace`]s captured by every [`TracedError`] in a `dyn Error`
+/// source chain, starting with the error the iterator was created from.
+///
+/// This is produced by [`ExtractSpanTrace::span_traces`].
+#[derive(Debug)]
+pub struct SpanTraces<'a> {
+    next: Option<&'a (dyn std::error::Error + 'static)>,
+}
+
+impl<'a> SpanTraces<'a> {
+    /// Create an iterator over every `SpanTrace` in `error`'s source chain, starting with
+    /// `error` itself.
+    pub fn new(error: &'a (dyn std::error::Error + 'static)) -> Self {
+        Self { next: Some(error) }
+    }
+}
+
+impl<'a> Iterator for SpanTraces<'a> {
+    type Item = &'a SpanTrace;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(error) = self.next {
+            self.next = error.source();
+            if let Some(span_trace) = error.span_trace() {
+                // An error that exposes the same `SpanTrace` as its source (e.g. a `TracedError`
+                // providing its trace and then its own placeholder source) is reported once.
+                let repeated_by_source = self
+                    .next
+                    .and_then(|next| next.span_trace())
+                    .map_or(false, |next| std::ptr::eq(next, span_trace));
+                if !repeated_by_source {
+                    return Some(span_trace);
+                }
+            }
+        }
+
+        None
+    }
 }
 
 /// The `tracing-error` prelude.
@@ -206,4 +292,44 @@ pub trait ExtractSpanTrace {
 /// attach Spantraces to errors and subsequently retrieve them from dyn Errors.
 pub mod prelude {
     pub use crate::{ExtractSpanTrace as _, InstrumentError as _, InstrumentResult as _};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt;
+
+    #[derive(Debug)]
+    struct PlainError(&'static str);
+
+    impl fmt::Display for PlainError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl std::error::Error for PlainError {}
+
+    #[test]
+    fn span_traces_walks_every_traced_error_in_the_chain() {
+        let inner = PlainError("inner").in_current_span();
+        let outer = inner.in_current_span();
+
+        let count = (&outer as &(dyn std::error::Error + 'static))
+            .span_traces()
+            .count();
+
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn span_traces_is_empty_for_an_untraced_error() {
+        let err = PlainError("untraced");
+
+        let count = (&err as &(dyn std::error::Error + 'static))
+            .span_traces()
+            .count();
+
+        assert_eq!(count, 0);
+    }
 }
\ No newline at end of file
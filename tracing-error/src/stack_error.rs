@@ -0,0 +1,188 @@
+use crate::{ExtractSpanTrace, InstrumentError, InstrumentResult, SpanTrace, SpanTraces};
+use std::error::Error;
+use std::fmt;
+
+/// A wrapper type for `Error`s that bundles a `SpanTrace` with an existing
+/// error, storing the wrapped error inline on the stack instead of behind a
+/// heap-allocated `Box`.
+///
+/// To let the `SpanTrace` (and the rest of the cause chain) still be recovered from a
+/// type-erased `dyn Error` without knowing `E`, `TracedError<E>`'s `source()` hands back a
+/// `&TraceDummy` instead of `&E` directly. `TraceDummy` is `TracedError<E>`'s first field under
+/// `#[repr(C)]`, so a `&TraceDummy` obtained this way shares an address with the `TracedError<E>`
+/// it came from; `TraceDummy` carries a per-`E` function pointer (captured when the
+/// `TracedError<E>` was constructed) that knows how to reinterpret that address back into a
+/// `&TracedError<E>` and continue on to `&self.error`. `ExtractSpanTrace::span_trace` recognizes
+/// a `TraceDummy` by downcasting to it and reads its `span_trace` field directly.
+#[repr(C)]
+pub struct TracedError<E> {
+    dummy: TraceDummy,
+    error: E,
+}
+
+struct TraceDummy {
+    span_trace: SpanTrace,
+    source: fn(&TraceDummy) -> Option<&(dyn Error + 'static)>,
+}
+
+impl fmt::Debug for TraceDummy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceDummy").finish()
+    }
+}
+
+impl fmt::Display for TraceDummy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "")
+    }
+}
+
+impl Error for TraceDummy {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        (self.source)(self)
+    }
+}
+
+fn source_of<E>(dummy: &TraceDummy) -> Option<&(dyn Error + 'static)>
+where
+    E: Error + 'static,
+{
+    // SAFETY: the only `&TraceDummy` this function is ever called with is `&traced.dummy` for
+    // some `traced: TracedError<E>` (see `TracedError::<E>::source`), and `dummy` is
+    // `TracedError<E>`'s first field under `#[repr(C)]`, so `dummy`'s address is the address of
+    // the `TracedError<E>` it was taken from.
+    let traced = unsafe { &*(dummy as *const TraceDummy as *const TracedError<E>) };
+    Some(&traced.error)
+}
+
+impl<E> From<E> for TracedError<E>
+where
+    E: Error + 'static,
+{
+    fn from(error: E) -> Self {
+        crate::layer::emit_error_event(&error);
+        Self {
+            dummy: TraceDummy {
+                span_trace: SpanTrace::capture(),
+                source: source_of::<E>,
+            },
+            error,
+        }
+    }
+}
+
+impl<E> fmt::Display for TracedError<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl<E> fmt::Debug for TracedError<E>
+where
+    E: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.error, f)?;
+        writeln!(f)?;
+        write!(f, "{}", self.dummy.span_trace)
+    }
+}
+
+impl<E> Error for TracedError<E>
+where
+    E: Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.dummy)
+    }
+
+    #[cfg(feature = "unstable-provider-api")]
+    fn provide<'a>(&'a self, request: &mut std::error::Request<'a>) {
+        request.provide_ref::<SpanTrace>(&self.dummy.span_trace);
+        self.error.provide(request);
+    }
+}
+
+impl<T, E> InstrumentResult<T> for Result<T, E>
+where
+    E: Error + 'static,
+{
+    type Instrumented = TracedError<E>;
+
+    fn in_current_span(self) -> Result<T, TracedError<E>> {
+        self.map_err(TracedError::from)
+    }
+}
+
+impl<E> InstrumentError for E
+where
+    E: Error + 'static,
+{
+    type Instrumented = TracedError<E>;
+
+    fn in_current_span(self) -> Self::Instrumented {
+        TracedError::from(self)
+    }
+}
+
+impl ExtractSpanTrace for dyn Error + 'static {
+    fn span_trace(&self) -> Option<&SpanTrace> {
+        #[cfg(feature = "unstable-provider-api")]
+        {
+            if let Some(span_trace) = std::error::request_ref::<SpanTrace>(self) {
+                return Some(span_trace);
+            }
+        }
+
+        self.downcast_ref::<TraceDummy>()
+            .map(|dummy| &dummy.span_trace)
+    }
+
+    fn span_traces(&self) -> SpanTraces<'_> {
+        SpanTraces::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct PlainError(&'static str);
+
+    impl fmt::Display for PlainError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl Error for PlainError {}
+
+    #[test]
+    fn source_chain_reaches_the_wrapped_error() {
+        let traced = PlainError("boom").in_current_span();
+
+        // The immediate `.source()` is the internal `TraceDummy` placeholder (needed so the
+        // outer `SpanTrace` stays reachable by downcasting); it delegates on to the real wrapped
+        // error one hop further.
+        let placeholder = traced.source().expect("TracedError should have a source");
+        let wrapped = placeholder
+            .source()
+            .expect("the TraceDummy placeholder should delegate to the wrapped error");
+
+        assert_eq!(wrapped.to_string(), "boom");
+    }
+
+    #[test]
+    fn nested_traced_errors_are_all_reachable_through_span_traces() {
+        let inner = PlainError("inner").in_current_span();
+        let outer = inner.in_current_span();
+
+        let count = (&outer as &(dyn Error + 'static)).span_traces().count();
+
+        assert_eq!(count, 2);
+    }
+}